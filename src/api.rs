@@ -0,0 +1,97 @@
+use crate::model::{Address, AddressBalance, AssetName, BalanceAddress, TokenBalance, TokenBalancePointer, Transaction, TxHash, Utxo, UtxoPointer};
+use chain_syncer::api::ChainSyncError;
+use redbit::redb::ReadTransaction;
+use redbit::*;
+use std::collections::HashMap;
+
+/// A page of results plus a cursor to resume from, mirroring the offset-free
+/// pagination used by admin-style APIs such as Garage's.
+pub struct Page<T, C> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<C>,
+}
+
+pub struct UtxoBalancePage {
+    pub utxos: Vec<Utxo>,
+    pub total_nano_erg: u64,
+    pub total_assets: HashMap<AssetName, u64>,
+    pub next_cursor: Option<UtxoPointer>,
+}
+
+pub struct AssetHolder {
+    pub address: Address,
+    pub amount: u64,
+}
+
+pub struct TransactionDetail {
+    pub hash: TxHash,
+    pub outputs: Vec<Utxo>,
+    pub resolved_inputs: Vec<Utxo>,
+}
+
+fn paginate<T: Clone, K: Ord>(mut items: Vec<T>, key_of: impl Fn(&T) -> K, cursor: Option<K>, limit: usize) -> (Vec<T>, Option<T>) {
+    items.sort_by(|a, b| key_of(a).cmp(&key_of(b)));
+    let start = match &cursor {
+        Some(cursor) => items.iter().position(|item| key_of(item) > *cursor).unwrap_or(items.len()),
+        None => 0,
+    };
+    let page: Vec<T> = items[start..].iter().take(limit).cloned().collect();
+    let next_cursor = if start + page.len() < items.len() { page.last().cloned() } else { None };
+    (page, next_cursor)
+}
+
+/// Lists UTXOs recorded for `address` along with running totals.
+///
+/// Note: the index does not yet track which UTXOs have been spent (see the
+/// materialized balance work), so this currently returns every UTXO ever
+/// created for the address rather than only the unspent set. The totals,
+/// however, come from the materialized `AddressBalance` view and so reflect
+/// the address's actual unspent balance, not a sum over the page above.
+pub fn list_utxos_by_address(read_tx: &ReadTransaction, address: &Address, cursor: Option<UtxoPointer>, limit: usize) -> Result<UtxoBalancePage, ChainSyncError> {
+    let utxos = Utxo::get_by_address(read_tx, address)?;
+    let (page, next_cursor) = paginate(utxos, |u| u.id.clone(), cursor, limit);
+
+    let balance = AddressBalance::get(read_tx, &BalanceAddress(address.0.clone()))?;
+    let total_nano_erg = balance.as_ref().map_or(0, |b| b.nano_erg);
+    let total_assets: HashMap<AssetName, u64> = balance.map_or_else(HashMap::new, |b| b.tokens.into_iter().map(|t| (t.name, t.amount)).collect());
+
+    Ok(UtxoBalancePage { utxos: page, total_nano_erg, total_assets, next_cursor: next_cursor.map(|u| u.id) })
+}
+
+/// Lists every address currently holding a non-zero amount of `asset_name`, derived from the
+/// materialized `TokenBalance` view so each holder appears once with its actual current amount,
+/// rather than once per historical `Asset` row (which doesn't dedupe per address, net transfers,
+/// or exclude assets sitting in spent utxos).
+pub fn list_asset_holders(read_tx: &ReadTransaction, asset_name: &AssetName, cursor: Option<TokenBalancePointer>, limit: usize) -> Result<Page<AssetHolder, TokenBalancePointer>, ChainSyncError> {
+    let balances = TokenBalance::get_by_name(read_tx, asset_name)?;
+    let holders: Vec<(TokenBalancePointer, AssetHolder)> = balances
+        .into_iter()
+        .filter(|balance| balance.amount > 0)
+        .map(|balance| {
+            let address = Address(balance.id.parent.0.clone());
+            (balance.id.clone(), AssetHolder { address, amount: balance.amount })
+        })
+        .collect();
+
+    let (page, next_cursor_pair) = paginate(holders, |(pointer, _)| pointer.clone(), cursor, limit);
+    let next_cursor = next_cursor_pair.map(|(pointer, _)| pointer);
+    Ok(Page { items: page.into_iter().map(|(_, holder)| holder).collect(), next_cursor })
+}
+
+/// Resolves a transaction's outputs and its fully-resolved inputs by hash.
+pub fn get_transaction_detail(read_tx: &ReadTransaction, tx_hash: &TxHash) -> Result<Option<TransactionDetail>, ChainSyncError> {
+    let transactions: Vec<Transaction> = Transaction::get_by_hash(read_tx, tx_hash)?;
+    let Some(tx) = transactions.into_iter().next() else {
+        return Ok(None);
+    };
+
+    let mut resolved_inputs = Vec::with_capacity(tx.inputs.len());
+    for input in &tx.inputs {
+        let spent_utxo_pointer = UtxoPointer::from_parent(input.id.parent.clone(), input.id.index());
+        if let Some(utxo) = Utxo::get(read_tx, &spent_utxo_pointer)? {
+            resolved_inputs.push(utxo);
+        }
+    }
+
+    Ok(Some(TransactionDetail { hash: tx.hash, outputs: tx.utxos, resolved_inputs }))
+}
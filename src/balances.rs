@@ -0,0 +1,151 @@
+use crate::model::{
+    Address, AddressBalance, AddressDelta, AddressDeltaPointer, AssetName, BalanceAddress, Block, BlockBalanceDelta, BlockHeight, TokenBalance,
+    TokenBalancePointer, TokenDelta, TokenDeltaPointer, Utxo,
+};
+use chain_syncer::api::ChainSyncError;
+use redbit::redb::ReadTransaction;
+use redbit::*;
+use std::collections::HashMap;
+
+fn balance_address(address: &Address) -> BalanceAddress {
+    BalanceAddress(address.0.clone())
+}
+
+/// Per-address net change produced while processing a single block: nanoErg plus per-token deltas.
+#[derive(Default)]
+struct AddressDeltaAcc {
+    nano_erg_delta: i64,
+    token_deltas: HashMap<AssetName, i64>,
+}
+
+/// Computes the net balance delta a block produces, crediting every created `Utxo` and debiting
+/// the `Utxo` each resolved input spent. `pending_utxos` supplies outputs created earlier in the
+/// same batch that are not yet visible through `read_tx`.
+pub fn compute_block_deltas(block: &Block, pending_utxos: &HashMap<Vec<u8>, Utxo>, read_tx: &ReadTransaction) -> Result<BlockBalanceDelta, ChainSyncError> {
+    let mut by_address: HashMap<BalanceAddress, AddressDeltaAcc> = HashMap::new();
+
+    for tx in &block.transactions {
+        for utxo in &tx.utxos {
+            let acc = by_address.entry(balance_address(&utxo.address)).or_default();
+            acc.nano_erg_delta += utxo.amount as i64;
+            for asset in &utxo.assets {
+                *acc.token_deltas.entry(asset.name.clone()).or_insert(0) += asset.amount as i64;
+            }
+        }
+
+        for box_id in &tx.transient_inputs {
+            let spent = match pending_utxos.get(&box_id.0) {
+                Some(utxo) => Some(utxo.clone()),
+                None => Utxo::get_ids_by_box_id(read_tx, box_id)?.first().and_then(|pointer| Utxo::get(read_tx, pointer).ok().flatten()),
+            };
+            if let Some(utxo) = spent {
+                let acc = by_address.entry(balance_address(&utxo.address)).or_default();
+                acc.nano_erg_delta -= utxo.amount as i64;
+                for asset in &utxo.assets {
+                    *acc.token_deltas.entry(asset.name.clone()).or_insert(0) -= asset.amount as i64;
+                }
+            }
+        }
+    }
+
+    let address_deltas = by_address
+        .into_iter()
+        .enumerate()
+        .map(|(index, (address, acc))| {
+            let delta_pointer = AddressDeltaPointer::from_parent(block.id.clone(), index as u16);
+            let token_deltas = acc
+                .token_deltas
+                .into_iter()
+                .enumerate()
+                .map(|(token_index, (name, amount_delta))| TokenDelta {
+                    id: TokenDeltaPointer::from_parent(delta_pointer.clone(), token_index as u8),
+                    name,
+                    amount_delta,
+                })
+                .collect();
+            AddressDelta { id: delta_pointer, address, nano_erg_delta: acc.nano_erg_delta, token_deltas }
+        })
+        .collect();
+
+    Ok(BlockBalanceDelta { id: block.id.clone(), address_deltas })
+}
+
+fn load_balance(read_tx: &ReadTransaction, accumulator: &HashMap<BalanceAddress, AddressBalance>, address: &BalanceAddress) -> Result<AddressBalance, ChainSyncError> {
+    if let Some(balance) = accumulator.get(address) {
+        return Ok(balance.clone());
+    }
+    Ok(AddressBalance::get(read_tx, address)?.unwrap_or_else(|| AddressBalance { id: address.clone(), nano_erg: 0, tokens: vec![] }))
+}
+
+/// Applies `address_delta` to `balance` with the given `sign` (`1` to apply, `-1` to reverse).
+///
+/// A resulting negative balance means the delta history and the materialized view have
+/// diverged (a bug elsewhere, not a legitimate state), so this surfaces an error instead of
+/// silently clamping to zero and letting the corruption go unnoticed.
+fn apply_delta_sign(balance: &mut AddressBalance, address_delta: &AddressDelta, sign: i64) -> Result<(), ChainSyncError> {
+    let nano_erg = balance.nano_erg as i64 + sign * address_delta.nano_erg_delta;
+    if nano_erg < 0 {
+        return Err(ChainSyncError::new(&format!("negative nano_erg balance for address {:?}: {}", balance.id, nano_erg)));
+    }
+    balance.nano_erg = nano_erg as u64;
+
+    let mut tokens: HashMap<AssetName, u64> = balance.tokens.drain(..).map(|t| (t.name, t.amount)).collect();
+    for token_delta in &address_delta.token_deltas {
+        let amount = tokens.entry(token_delta.name.clone()).or_insert(0);
+        let updated = *amount as i64 + sign * token_delta.amount_delta;
+        if updated < 0 {
+            return Err(ChainSyncError::new(&format!("negative token balance for address {:?}, token {:?}: {}", balance.id, token_delta.name, updated)));
+        }
+        *amount = updated as u64;
+    }
+    balance.tokens = tokens
+        .into_iter()
+        .enumerate()
+        .map(|(index, (name, amount))| TokenBalance { id: TokenBalancePointer::from_parent(balance.id.clone(), index as u16), name, amount })
+        .collect();
+    Ok(())
+}
+
+/// Folds `delta` into `accumulator`, reading the current persisted balance via `read_tx` the
+/// first time an address is touched within the batch.
+pub fn apply_deltas(read_tx: &ReadTransaction, delta: &BlockBalanceDelta, accumulator: &mut HashMap<BalanceAddress, AddressBalance>) -> Result<(), ChainSyncError> {
+    for address_delta in &delta.address_deltas {
+        let mut balance = load_balance(read_tx, accumulator, &address_delta.address)?;
+        apply_delta_sign(&mut balance, address_delta, 1)?;
+        accumulator.insert(address_delta.address.clone(), balance);
+    }
+    Ok(())
+}
+
+/// Undoes `delta` from `accumulator`, used by `update_blocks` to roll back orphaned blocks
+/// before the replacement blocks are applied.
+pub fn reverse_deltas(read_tx: &ReadTransaction, delta: &BlockBalanceDelta, accumulator: &mut HashMap<BalanceAddress, AddressBalance>) -> Result<(), ChainSyncError> {
+    for address_delta in &delta.address_deltas {
+        let mut balance = load_balance(read_tx, accumulator, &address_delta.address)?;
+        apply_delta_sign(&mut balance, address_delta, -1)?;
+        accumulator.insert(address_delta.address.clone(), balance);
+    }
+    Ok(())
+}
+
+pub fn store_balances(write_tx: &redb::WriteTransaction, balances: &HashMap<BalanceAddress, AddressBalance>) -> Result<(), ChainSyncError> {
+    for balance in balances.values() {
+        let _ = AddressBalance::delete(write_tx, &balance.id);
+        AddressBalance::store(write_tx, balance)?;
+    }
+    Ok(())
+}
+
+pub fn store_deltas(write_tx: &redb::WriteTransaction, deltas: &[BlockBalanceDelta]) -> Result<(), ChainSyncError> {
+    for delta in deltas {
+        BlockBalanceDelta::store(write_tx, delta)?;
+    }
+    Ok(())
+}
+
+pub fn delete_deltas(write_tx: &redb::WriteTransaction, heights: &[BlockHeight]) -> Result<(), ChainSyncError> {
+    for height in heights {
+        let _ = BlockBalanceDelta::delete(write_tx, height);
+    }
+    Ok(())
+}
@@ -1,19 +1,41 @@
-use crate::model::{Block, BlockHash, BlockHeader, BlockHeight, InputPointer, InputRef, TxPointer, Utxo};
+use crate::balances;
+use crate::metrics;
+use crate::model::{
+    AddressBalance, AssetKey, BalanceAddress, Block, BlockBalanceDelta, BlockHash, BlockHeader, BlockHeight, BlockMintedTokens, InputPointer, InputRef,
+    MintedAssetId, MintedTokenPointer, MintedTokenRef, RegisterBytes, TokenRegistry, TxPointer, Utxo,
+};
 use chain_syncer::api::*;
 use redbit::redb::ReadTransaction;
 use redbit::*;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+/// Number of blocks persisted per redb write transaction when no explicit batch size is configured.
+pub const DEFAULT_BATCH_SIZE: usize = 100;
 
 pub struct ErgoBlockPersistence {
     pub db: Arc<redb::Database>,
+    pub batch_size: usize,
 }
 
 impl ErgoBlockPersistence {
-    fn populate_inputs(read_tx: &ReadTransaction, block: &mut Block) -> Result<(), ChainSyncError> {
+    pub fn new(db: Arc<redb::Database>, batch_size: usize) -> Self {
+        ErgoBlockPersistence { db, batch_size }
+    }
+
+    /// Resolves `transient_inputs` to `InputRef`s, preferring the in-memory `pending` map of
+    /// outputs created earlier in the current batch (not yet visible to `read_tx`) before
+    /// falling back to the on-disk `Utxo` index.
+    fn populate_inputs(read_tx: &ReadTransaction, pending: &HashMap<Vec<u8>, Utxo>, block: &mut Block) -> Result<(), ChainSyncError> {
         for tx in &mut block.transactions {
             for box_id in tx.transient_inputs.iter_mut() {
-                let utxo_pointers = Utxo::get_ids_by_box_id(read_tx, &box_id).expect("Failed to get Utxo by ErgoBox");
-                match utxo_pointers.first() {
+                let resolved = match pending.get(&box_id.0) {
+                    Some(utxo) => Some(utxo.id.clone()),
+                    None => Utxo::get_ids_by_box_id(read_tx, box_id).expect("Failed to get Utxo by ErgoBox").into_iter().next(),
+                };
+                match resolved {
                     Some(utxo_pointer) => {
                         tx.inputs.push(InputRef { id: InputPointer::from_parent(utxo_pointer.parent.clone(), utxo_pointer.index()) })
                     }
@@ -23,6 +45,67 @@ impl ErgoBlockPersistence {
         }
         Ok(())
     }
+
+    /// Records the outputs of `block` into `pending` so later blocks in the same batch can
+    /// resolve inputs spending them (and have their balance debited) before anything is committed.
+    fn track_pending_outputs(block: &Block, pending: &mut HashMap<Vec<u8>, Utxo>) {
+        for tx in &block.transactions {
+            for utxo in &tx.utxos {
+                pending.insert(utxo.box_id.0.clone(), utxo.clone());
+            }
+        }
+    }
+
+    /// Writes the `TokenRegistry` row for each token genuinely minted in `block`, plus a
+    /// `BlockMintedTokens` side record so `update_blocks` can find and delete them again if this
+    /// block is later orphaned by a reorg.
+    fn store_minted_tokens(write_tx: &redb::WriteTransaction, block: &Block) -> Result<(), ChainSyncError> {
+        if block.minted_tokens.is_empty() {
+            return Ok(());
+        }
+        let mut token_refs = Vec::with_capacity(block.minted_tokens.len());
+        for (index, minted) in block.minted_tokens.iter().enumerate() {
+            let registry = TokenRegistry {
+                id: minted.asset_key.clone(),
+                issuing_height: minted.issuing_tx.parent.0,
+                issuing_tx_index: minted.issuing_tx.index(),
+                total_issued: minted.total_issued,
+                registers: RegisterBytes(minted.registers.clone()),
+            };
+            TokenRegistry::store(write_tx, &registry)?;
+            token_refs.push(MintedTokenRef {
+                id: MintedTokenPointer::from_parent(block.id.clone(), index as u8),
+                asset_id: MintedAssetId(minted.asset_key.0.clone()),
+            });
+        }
+        BlockMintedTokens::store(write_tx, &BlockMintedTokens { id: block.id.clone(), tokens: token_refs })?;
+        Ok(())
+    }
+
+    /// Deletes the `TokenRegistry` rows (and the `BlockMintedTokens` side record itself) that
+    /// `store_minted_tokens` wrote for `height`, so an orphaned minting block doesn't leave a
+    /// stale registry entry behind after a reorg.
+    fn delete_minted_tokens(read_tx: &ReadTransaction, write_tx: &redb::WriteTransaction, height: &BlockHeight) -> Result<(), ChainSyncError> {
+        if let Some(minted) = BlockMintedTokens::get(read_tx, height)? {
+            for token_ref in &minted.tokens {
+                let _ = TokenRegistry::delete(write_tx, &AssetKey(token_ref.asset_id.0.clone()));
+            }
+        }
+        let _ = BlockMintedTokens::delete(write_tx, height);
+        Ok(())
+    }
+
+    fn record_stored_metrics(block: &Block) {
+        let metrics = metrics::metrics();
+        metrics.blocks_indexed_total.fetch_add(1, Ordering::Relaxed);
+        metrics.indexed_height.store(block.id.0 as u64, Ordering::Relaxed);
+        metrics.transactions_stored_total.fetch_add(block.transactions.len() as u64, Ordering::Relaxed);
+        let (utxo_count, asset_count) = block.transactions.iter().fold((0u64, 0u64), |(utxos, assets), tx| {
+            (utxos + tx.utxos.len() as u64, assets + tx.utxos.iter().map(|u| u.assets.len() as u64).sum::<u64>())
+        });
+        metrics.utxos_stored_total.fetch_add(utxo_count, Ordering::Relaxed);
+        metrics.assets_stored_total.fetch_add(asset_count, Ordering::Relaxed);
+    }
 }
 
 impl BlockPersistence<Block> for ErgoBlockPersistence {
@@ -39,26 +122,73 @@ impl BlockPersistence<Block> for ErgoBlockPersistence {
     }
 
     fn store_blocks(&self, mut blocks: Vec<Block>) -> Result<(), ChainSyncError> {
-        for block in &mut blocks {
+        let batch_size = self.batch_size.max(1);
+        for chunk in blocks.chunks_mut(batch_size) {
+            let mut pending: HashMap<Vec<u8>, Utxo> = HashMap::new();
+            let mut deltas: Vec<BlockBalanceDelta> = Vec::with_capacity(chunk.len());
+            let mut balance_updates: HashMap<BalanceAddress, AddressBalance> = HashMap::new();
             {
                 let read_tx = self.db.begin_read()?;
-                Self::populate_inputs(&read_tx, block)?;
+                metrics::metrics().redb_read_transactions_total.fetch_add(1, Ordering::Relaxed);
+                for block in chunk.iter_mut() {
+                    Self::populate_inputs(&read_tx, &pending, block)?;
+                    let delta = balances::compute_block_deltas(block, &pending, &read_tx)?;
+                    balances::apply_deltas(&read_tx, &delta, &mut balance_updates)?;
+                    deltas.push(delta);
+                    Self::track_pending_outputs(block, &mut pending);
+                }
             }
             {
                 let write_ins = self.db.begin_write()?;
-                Block::store(&write_ins, block)?;
+                metrics::metrics().redb_write_transactions_total.fetch_add(1, Ordering::Relaxed);
+                for block in chunk.iter() {
+                    Block::store(&write_ins, block)?;
+                    Self::store_minted_tokens(&write_ins, block)?;
+                }
+                balances::store_deltas(&write_ins, &deltas)?;
+                balances::store_balances(&write_ins, &balance_updates)?;
+                let commit_started_at = Instant::now();
                 write_ins.commit()?;
+                metrics::metrics().observe_commit_latency(commit_started_at.elapsed());
+            }
+            for block in chunk.iter() {
+                Self::record_stored_metrics(block);
             }
         }
         Ok(())
     }
 
     fn update_blocks(&self, mut blocks: Vec<Block>) -> Result<(), ChainSyncError> {
-        let write_tx = self.db.begin_write()?;
-        for block in &mut blocks {
-            Block::delete(&write_tx, &block.id)?;
+        let heights: Vec<BlockHeight> = blocks.iter().map(|b| b.id.clone()).collect();
+        let mut balance_rollback: HashMap<BalanceAddress, AddressBalance> = HashMap::new();
+        {
+            let read_tx = self.db.begin_read()?;
+            metrics::metrics().redb_read_transactions_total.fetch_add(1, Ordering::Relaxed);
+            // Reverse newest-to-oldest: apply_delta_sign now hard-errors on a transient negative
+            // balance, and reversing in ascending height order can manufacture one (e.g. an
+            // address credited at height 100 whose utxo is spent away at height 101 has net
+            // balance 0, but reversing height 100 first computes 0 - 10 before height 101's
+            // reversal would have added the 10 back).
+            let mut reversal_heights = heights.clone();
+            reversal_heights.sort_by(|a, b| b.0.cmp(&a.0));
+            for height in &reversal_heights {
+                if let Some(delta) = BlockBalanceDelta::get(&read_tx, height)? {
+                    balances::reverse_deltas(&read_tx, &delta, &mut balance_rollback)?;
+                }
+            }
+
+            let write_tx = self.db.begin_write()?;
+            metrics::metrics().redb_write_transactions_total.fetch_add(1, Ordering::Relaxed);
+            for height in &heights {
+                Self::delete_minted_tokens(&read_tx, &write_tx, height)?;
+            }
+            for block in &mut blocks {
+                Block::delete(&write_tx, &block.id)?;
+            }
+            balances::delete_deltas(&write_tx, &heights)?;
+            balances::store_balances(&write_tx, &balance_rollback)?;
+            write_tx.commit()?;
         }
-        write_tx.commit()?;
         self.store_blocks(blocks)?;
         Ok(())
     }
@@ -1,9 +1,10 @@
-use crate::config::ErgoConfig;
+use crate::config::{ErgoConfig, RetrySettings};
 use crate::ergo_client::ErgoClient;
+use crate::metrics;
 use crate::model;
 use crate::model::{
-    Address, Asset, AssetAction, AssetName, AssetPointer, AssetType, Block, BlockHash, BlockHeader, BlockHeight, BlockTimestamp, Transaction, TxHash,
-    TxPointer, Utxo, UtxoPointer,
+    Address, Asset, AssetAction, AssetName, AssetPointer, AssetType, Block, BlockHash, BlockHeader, BlockHeight, BlockTimestamp, ExplorerError, Transaction,
+    TxHash, TxPointer, Utxo, UtxoPointer,
 };
 use async_trait::async_trait;
 use chain_syncer::api::{BlockProvider, ChainSyncError};
@@ -21,11 +22,17 @@ use futures::Stream;
 use futures::stream::StreamExt;
 use redbit::*;
 use reqwest::Url;
-use std::{pin::Pin, str::FromStr, sync::Arc};
+use std::{
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 pub struct ErgoBlockProvider {
     pub client: Arc<ErgoClient>,
     pub fetching_par: usize,
+    pub retry: RetrySettings,
 }
 
 impl ErgoBlockProvider {
@@ -33,10 +40,65 @@ impl ErgoBlockProvider {
         ErgoBlockProvider {
             client: Arc::new(ErgoClient { node_url: Url::from_str(&ergo_config.api_host).unwrap(), api_key: ergo_config.api_key.clone() }),
             fetching_par,
+            retry: ergo_config.retry.clone(),
+        }
+    }
+
+    /// Deterministic errors (malformed responses, bad config) are not worth retrying; only
+    /// transport failures and server-side (5xx) errors are.
+    fn is_retryable(error: &ExplorerError) -> bool {
+        match error {
+            ExplorerError::Reqwest { source } => source.is_timeout() || source.is_connect() || source.is_request() || source.status().is_some_and(|status| status.is_server_error()),
+            ExplorerError::Url(_) | ExplorerError::Custom(_) => false,
+        }
+    }
+
+    /// Jitter in [0, max_jitter) derived from the current time, avoiding a dependency on a
+    /// random number generator crate for a one-off backoff nudge.
+    fn jitter(max_jitter: Duration) -> Duration {
+        if max_jitter.is_zero() {
+            return Duration::ZERO;
+        }
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+        Duration::from_nanos((nanos as u64) % (max_jitter.as_nanos() as u64).max(1))
+    }
+
+    /// A minting tx can legally split the newly-issued token across more than one output box;
+    /// aggregate those into a single registry row per `asset_key` instead of writing one row per
+    /// output (which would undercount `total_issued` or collide on the registry's primary key).
+    fn merge_minted_tokens(tokens: Vec<model::MintedToken>) -> Vec<model::MintedToken> {
+        let mut merged: Vec<model::MintedToken> = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            match merged.iter_mut().find(|existing| existing.asset_key == token.asset_key) {
+                Some(existing) => existing.total_issued += token.total_issued,
+                None => merged.push(token),
+            }
+        }
+        merged
+    }
+
+    async fn fetch_with_retry(client: Arc<ErgoClient>, height: BlockHeight, retry: &RetrySettings) -> Result<FullBlock, ChainSyncError> {
+        let mut attempt = 0u32;
+        let mut delay = retry.base_delay();
+        loop {
+            match client.get_block_by_height_async(height.clone()).await {
+                Ok(block) => return Ok(block),
+                Err(e) if attempt < retry.max_retries && Self::is_retryable(&e) => {
+                    attempt += 1;
+                    let sleep_for = delay + Self::jitter(delay);
+                    info!("Retrying block {} fetch (attempt {}/{}) after error: {} (sleeping {:?})", height.0, attempt, retry.max_retries, e, sleep_for);
+                    tokio::time::sleep(sleep_for).await;
+                    delay = (delay * 2).min(retry.max_delay());
+                }
+                Err(e) => return Err(ChainSyncError::new(&format!("failed to fetch block {} after {} attempts: {}", height.0, attempt + 1, e))),
+            }
         }
     }
-    fn process_outputs(&self, outs: &[ErgoBox], tx_pointer: TxPointer) -> (BoxWeight, Vec<Utxo>) {
+    /// `minted_token_id` is the transaction's first input box id: on Ergo a token is minted
+    /// exactly when an output asset's id matches it, not the first output's box id.
+    fn process_outputs(&self, outs: &[ErgoBox], tx_pointer: TxPointer, minted_token_id: Option<TokenId>) -> (BoxWeight, Vec<Utxo>, Vec<model::MintedToken>) {
         let mut result_outs = Vec::with_capacity(outs.len());
+        let mut minted_tokens = Vec::new();
         let mut asset_count = 0;
         for (out_index, out) in outs.iter().enumerate() {
             let box_id = out.box_id();
@@ -56,15 +118,21 @@ impl ErgoBlockProvider {
                     let asset_id: Vec<u8> = asset.token_id.into();
                     let amount = asset.amount;
                     let amount_u64: u64 = amount.into();
-                    let is_mint = outs.first().is_some_and(|o| {
-                        let new_token_id: TokenId = o.box_id().into();
-                        new_token_id == asset.token_id
-                    });
+                    let is_mint = minted_token_id.is_some_and(|token_id| token_id == asset.token_id);
 
                     let action = match is_mint {
-                        true => AssetType::Mint, // TODO!! for Minting it might not be enough to check first boxId
+                        true => AssetType::Mint,
                         _ => AssetType::Transfer,
                     };
+                    if is_mint {
+                        let registers = out.additional_registers.sigma_serialize_bytes().ok().unwrap_or_default();
+                        minted_tokens.push(model::MintedToken {
+                            asset_key: model::AssetKey(asset_id.clone()),
+                            issuing_tx: tx_pointer.clone(),
+                            total_issued: amount_u64,
+                            registers,
+                        });
+                    }
                     let asset_pointer = AssetPointer::from_parent(utxo_pointer.clone(), index as u8);
                     result.push(Asset { id: asset_pointer, name: AssetName(asset_id), amount: amount_u64, asset_action: AssetAction(action.into()) });
                 }
@@ -84,7 +152,7 @@ impl ErgoBlockProvider {
                 tree_t8: model::TreeT8(ergo_tree_t8_opt.unwrap_or(vec![])),
             })
         }
-        (asset_count + result_outs.len(), result_outs)
+        (asset_count + result_outs.len(), result_outs, minted_tokens)
     }
 }
 
@@ -93,6 +161,7 @@ impl BlockProvider<FullBlock, Block> for ErgoBlockProvider {
     fn process_block(&self, b: &FullBlock) -> Result<Block, ChainSyncError> {
         let mut block_weight: usize = 0;
         let mut result_txs = Vec::with_capacity(b.block_transactions.transactions.len());
+        let mut minted_tokens = Vec::new();
 
         let block_hash: [u8; 32] = b.header.id.0.into();
         let prev_block_hash: [u8; 32] = b.header.parent_id.0.into();
@@ -108,7 +177,8 @@ impl BlockProvider<FullBlock, Block> for ErgoBlockProvider {
         for (tx_index, tx) in b.block_transactions.transactions.iter().enumerate() {
             let tx_hash: [u8; 32] = tx.id().0.0;
             let tx_id = TxPointer::from_parent(header.id.clone(), tx_index as u16);
-            let (box_weight, outputs) = self.process_outputs(&tx.outputs().to_vec(), tx_id.clone()); //TODO perf check
+            let minted_token_id: Option<TokenId> = tx.inputs.first().map(|input| input.box_id.clone().into());
+            let (box_weight, outputs, tx_minted_tokens) = self.process_outputs(&tx.outputs().to_vec(), tx_id.clone(), minted_token_id); //TODO perf check
             let inputs: Vec<model::BoxId> = tx
                 .inputs
                 .iter()
@@ -120,10 +190,12 @@ impl BlockProvider<FullBlock, Block> for ErgoBlockProvider {
                 .collect();
             block_weight += box_weight;
             block_weight += tx.inputs.len();
+            minted_tokens.extend(Self::merge_minted_tokens(tx_minted_tokens));
             result_txs.push(Transaction { id: tx_id.clone(), hash: TxHash(tx_hash), utxos: outputs, inputs: vec![], transient_inputs: inputs })
         }
 
-        Ok(Block { id: id.clone(), header, transactions: result_txs, weight: block_weight as u32 })
+        metrics::metrics().box_weight_processed_total.fetch_add(block_weight as u64, std::sync::atomic::Ordering::Relaxed);
+        Ok(Block { id: id.clone(), header, transactions: result_txs, weight: block_weight as u32, minted_tokens })
     }
 
     fn get_processed_block(&self, header: BlockHeader) -> Result<Block, ChainSyncError> {
@@ -134,6 +206,7 @@ impl BlockProvider<FullBlock, Block> for ErgoBlockProvider {
     async fn get_chain_tip(&self) -> Result<BlockHeader, ChainSyncError> {
         let best_block = self.client.get_best_block_async().await?;
         let processed_block = self.process_block(&best_block)?;
+        metrics::metrics().chain_tip_height.store(processed_block.header.id.0 as u64, std::sync::atomic::Ordering::Relaxed);
         Ok(processed_block.header)
     }
 
@@ -141,20 +214,22 @@ impl BlockProvider<FullBlock, Block> for ErgoBlockProvider {
         &self,
         chain_tip_header: BlockHeader,
         last_header: Option<BlockHeader>,
-    ) -> Pin<Box<dyn Stream<Item = FullBlock> + Send + 'life0>> {
+    ) -> Pin<Box<dyn Stream<Item = Result<FullBlock, ChainSyncError>> + Send + 'life0>> {
         let last_height = last_header.map_or(1, |h| h.id.0);
         info!("Indexing from {} to {}", last_height, chain_tip_header.id.0);
         let heights = last_height..=chain_tip_header.id.0;
+        let retry = self.retry.clone();
 
         tokio_stream::iter(heights)
-            .map(|height| {
+            .map(move |height| {
                 let client = Arc::clone(&self.client);
-                tokio::task::spawn(async move { client.get_block_by_height_async(BlockHeight(height)).await.unwrap() })
+                let retry = retry.clone();
+                tokio::task::spawn(async move { Self::fetch_with_retry(client, BlockHeight(height), &retry).await })
             })
             .buffered(self.fetching_par)
             .map(|res| match res {
-                Ok(block) => block,
-                Err(e) => panic!("Error: {:?}", e), // lousy error handling
+                Ok(block_result) => block_result,
+                Err(join_error) => Err(ChainSyncError::new(&format!("block fetch task panicked: {}", join_error))),
             })
             .boxed()
     }
@@ -0,0 +1,84 @@
+use crate::block_persistence::DEFAULT_BATCH_SIZE;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Bounded exponential-backoff policy for retrying transient node fetch failures.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetrySettings {
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub max_retries: u32,
+}
+
+impl RetrySettings {
+    pub fn base_delay(&self) -> Duration {
+        Duration::from_millis(self.base_delay_ms)
+    }
+
+    pub fn max_delay(&self) -> Duration {
+        Duration::from_millis(self.max_delay_ms)
+    }
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        RetrySettings { base_delay_ms: 200, max_delay_ms: 10_000, max_retries: 8 }
+    }
+}
+
+fn default_batch_size() -> usize {
+    DEFAULT_BATCH_SIZE
+}
+
+fn default_metrics_bind_address() -> SocketAddr {
+    "0.0.0.0:9100".parse().unwrap()
+}
+
+fn default_metrics_path() -> String {
+    "/metrics".to_string()
+}
+
+/// Where (and whether) the Prometheus `/metrics` server listens, independent of the main
+/// redbit http server's bind address.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MetricsSettings {
+    #[serde(default = "default_enabled")]
+    pub enable: bool,
+    #[serde(default = "default_metrics_bind_address")]
+    pub bind_address: SocketAddr,
+    #[serde(default = "default_metrics_path")]
+    pub path: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        MetricsSettings { enable: true, bind_address: default_metrics_bind_address(), path: default_metrics_path() }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErgoConfig {
+    pub api_host: String,
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub retry: RetrySettings,
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    #[serde(default)]
+    pub metrics: MetricsSettings,
+}
+
+impl ErgoConfig {
+    pub fn new(path: &str) -> anyhow::Result<Self> {
+        let settings = config::Config::builder()
+            .add_source(config::File::with_name(path))
+            .add_source(config::Environment::with_prefix("ERGO").separator("__"))
+            .build()?;
+        Ok(settings.try_deserialize()?)
+    }
+}
@@ -1,13 +1,16 @@
+mod api;
+mod balances;
 mod block_persistence;
 mod block_provider;
 mod config;
 mod ergo_client;
+mod metrics;
 mod model;
 mod storage;
 
 use crate::block_persistence::ErgoBlockPersistence;
 use crate::block_provider::ErgoBlockProvider;
-use crate::config::ErgoConfig;
+use crate::config::{ErgoConfig, MetricsSettings};
 use crate::model::Block;
 use anyhow::Result;
 use chain_syncer::api::{BlockPersistence, BlockProvider};
@@ -30,6 +33,14 @@ async fn maybe_run_server(http_conf: &HttpSettings, db: Arc<Database>) -> () {
     }
 }
 
+async fn maybe_run_metrics_server(metrics_conf: &MetricsSettings) -> () {
+    if metrics_conf.enable {
+        metrics::serve(metrics_conf.bind_address, &metrics_conf.path).await
+    } else {
+        ready(()).await
+    }
+}
+
 async fn maybe_run_indexing(index_config: &IndexerSettings, scheduler: Scheduler<FullBlock, Block>) -> () {
     if index_config.enable {
         info!("Starting indexing process");
@@ -48,11 +59,12 @@ async fn main() -> Result<()> {
     let fetching_par: usize = app_config.indexer.fetching_parallelism.clone().into();
     
     let block_provider: Arc<dyn BlockProvider<FullBlock, Block>> = Arc::new(ErgoBlockProvider::new(&ergo_config, fetching_par));
-    let block_persistence: Arc<dyn BlockPersistence<Block>> = Arc::new(ErgoBlockPersistence { db: Arc::clone(&db) });
+    let block_persistence: Arc<dyn BlockPersistence<Block>> = Arc::new(ErgoBlockPersistence::new(Arc::clone(&db), ergo_config.batch_size));
     let scheduler: Scheduler<FullBlock, Block> = Scheduler::new(block_provider, block_persistence);
 
     let indexing_f = maybe_run_indexing(&app_config.indexer, scheduler);
     let server_f = maybe_run_server(&app_config.http, Arc::clone(&db));
-    combine::futures(indexing_f, server_f).await;
+    let metrics_f = maybe_run_metrics_server(&ergo_config.metrics);
+    combine::futures(indexing_f, combine::futures(server_f, metrics_f)).await;
     Ok(())
 }
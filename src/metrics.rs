@@ -0,0 +1,154 @@
+use chain_syncer::info;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Fixed-bucket histogram (upper bounds, in milliseconds) for `store_blocks` commit latency.
+const COMMIT_LATENCY_BUCKETS_MS: [f64; 10] = [1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 5000.0];
+
+struct Histogram {
+    bucket_counts: Vec<AtomicU64>,
+    sum_ms: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Histogram { bucket_counts: (0..COMMIT_LATENCY_BUCKETS_MS.len()).map(|_| AtomicU64::new(0)).collect(), sum_ms: AtomicU64::new(0), count: AtomicU64::new(0) }
+    }
+
+    fn observe(&self, duration: Duration) {
+        let ms = duration.as_secs_f64() * 1000.0;
+        // Each bucket stores its own (non-cumulative) count; render() accumulates them into the
+        // cumulative `le` counts Prometheus expects.
+        if let Some(bucket_index) = COMMIT_LATENCY_BUCKETS_MS.iter().position(|upper_bound| ms <= *upper_bound) {
+            self.bucket_counts[bucket_index].fetch_add(1, Ordering::Relaxed);
+        }
+        self.sum_ms.fetch_add(ms as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str) {
+        let mut cumulative = 0u64;
+        for (upper_bound, bucket) in COMMIT_LATENCY_BUCKETS_MS.iter().zip(self.bucket_counts.iter()) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, upper_bound, cumulative);
+        }
+        let total = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, total);
+        let _ = writeln!(out, "{}_sum {}", name, self.sum_ms.load(Ordering::Relaxed));
+        let _ = writeln!(out, "{}_count {}", name, total);
+    }
+}
+
+/// Process-wide indexing and storage counters, exposed in the Prometheus text exposition format.
+pub struct Metrics {
+    pub blocks_indexed_total: AtomicU64,
+    pub transactions_stored_total: AtomicU64,
+    pub utxos_stored_total: AtomicU64,
+    pub assets_stored_total: AtomicU64,
+    pub box_weight_processed_total: AtomicU64,
+    pub indexed_height: AtomicU64,
+    pub chain_tip_height: AtomicU64,
+    pub redb_read_transactions_total: AtomicU64,
+    pub redb_write_transactions_total: AtomicU64,
+    store_blocks_commit_latency_ms: Histogram,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        Metrics {
+            blocks_indexed_total: AtomicU64::new(0),
+            transactions_stored_total: AtomicU64::new(0),
+            utxos_stored_total: AtomicU64::new(0),
+            assets_stored_total: AtomicU64::new(0),
+            box_weight_processed_total: AtomicU64::new(0),
+            indexed_height: AtomicU64::new(0),
+            chain_tip_height: AtomicU64::new(0),
+            redb_read_transactions_total: AtomicU64::new(0),
+            redb_write_transactions_total: AtomicU64::new(0),
+            store_blocks_commit_latency_ms: Histogram::new(),
+        }
+    }
+
+    pub fn observe_commit_latency(&self, duration: Duration) {
+        self.store_blocks_commit_latency_ms.observe(duration);
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let counter = |out: &mut String, name: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        };
+        let gauge = |out: &mut String, name: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        };
+
+        counter(&mut out, "ergo_explorer_blocks_indexed_total", "Total number of blocks indexed", self.blocks_indexed_total.load(Ordering::Relaxed));
+        counter(&mut out, "ergo_explorer_transactions_stored_total", "Total number of transactions stored", self.transactions_stored_total.load(Ordering::Relaxed));
+        counter(&mut out, "ergo_explorer_utxos_stored_total", "Total number of utxos stored", self.utxos_stored_total.load(Ordering::Relaxed));
+        counter(&mut out, "ergo_explorer_assets_stored_total", "Total number of assets stored", self.assets_stored_total.load(Ordering::Relaxed));
+        counter(&mut out, "ergo_explorer_box_weight_processed_total", "Cumulative box weight processed", self.box_weight_processed_total.load(Ordering::Relaxed));
+        gauge(&mut out, "ergo_explorer_indexed_height", "Height of the last block persisted", self.indexed_height.load(Ordering::Relaxed));
+        gauge(&mut out, "ergo_explorer_chain_tip_height", "Height of the chain tip as last observed", self.chain_tip_height.load(Ordering::Relaxed));
+        counter(&mut out, "ergo_explorer_redb_read_transactions_total", "Total number of redb read transactions opened by store_blocks and update_blocks", self.redb_read_transactions_total.load(Ordering::Relaxed));
+        counter(&mut out, "ergo_explorer_redb_write_transactions_total", "Total number of redb write transactions opened", self.redb_write_transactions_total.load(Ordering::Relaxed));
+
+        let _ = writeln!(out, "# HELP ergo_explorer_store_blocks_commit_latency_ms Latency of store_blocks commits in milliseconds");
+        let _ = writeln!(out, "# TYPE ergo_explorer_store_blocks_commit_latency_ms histogram");
+        self.store_blocks_commit_latency_ms.render(&mut out, "ergo_explorer_store_blocks_commit_latency_ms");
+
+        out
+    }
+}
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+/// Process-wide metrics singleton, lazily initialized on first access.
+pub fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(Metrics::new)
+}
+
+/// Serves the Prometheus exposition text at `path` on `bind_address`, alongside the main http server.
+pub async fn serve(bind_address: SocketAddr, path: &str) {
+    let listener = match TcpListener::bind(bind_address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            info!("Failed to bind metrics listener at {}: {}", bind_address, e);
+            return;
+        }
+    };
+    info!("Starting metrics server at {}{}", bind_address, path);
+    let path = path.to_string();
+    loop {
+        let (mut socket, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(_) => continue,
+        };
+        let path = path.clone();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 1024];
+            let n = match socket.read(&mut buf).await {
+                Ok(n) => n,
+                Err(_) => return,
+            };
+            let request_line = String::from_utf8_lossy(&buf[..n]);
+            let requested_path = request_line.split_whitespace().nth(1).unwrap_or("/");
+            let response = if requested_path == path {
+                let body = metrics().render();
+                format!("HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}", body.len(), body)
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string()
+            };
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+    }
+}
@@ -9,6 +9,12 @@ pub struct AssetName(pub Vec<u8>);
 #[column]
 pub struct AssetAction(pub u8);
 #[column]
+pub struct RegisterBytes(pub Vec<u8>);
+#[root_key]
+pub struct AssetKey(pub Vec<u8>);
+#[column]
+pub struct MintedAssetId(pub Vec<u8>);
+#[column]
 pub struct Tree(pub Vec<u8>);
 #[column]
 pub struct TreeT8(pub Vec<u8>);
@@ -55,6 +61,56 @@ pub struct Block {
     pub transactions: Vec<Transaction>,
     #[column(transient)]
     pub weight: u32,
+    /// Tokens minted in this block, computed alongside `transactions` but stored separately
+    /// into the `TokenRegistry` by the persistence layer since it isn't keyed by `BlockHeight`.
+    #[column(transient)]
+    pub minted_tokens: Vec<MintedToken>,
+}
+
+/// A newly issued token discovered while processing a block, carried out-of-band until the
+/// persistence layer writes it into the `TokenRegistry`.
+#[derive(Clone, Debug)]
+pub struct MintedToken {
+    pub asset_key: AssetKey,
+    pub issuing_tx: TxPointer,
+    pub total_issued: u64,
+    pub registers: Vec<u8>,
+}
+
+/// Registry of genuinely minted tokens (as opposed to mere transfers), so downstream queries
+/// can distinguish issuance events and surface token metadata from the minting box's registers.
+#[entity]
+pub struct TokenRegistry {
+    #[pk]
+    pub id: AssetKey,
+    #[column]
+    pub issuing_height: u32,
+    #[column]
+    pub issuing_tx_index: u16,
+    #[column]
+    pub total_issued: u64,
+    #[column]
+    pub registers: RegisterBytes,
+}
+
+#[pointer_key(u8)]
+pub struct MintedTokenPointer(BlockHeight);
+
+/// Tracks which `TokenRegistry` rows a block produced, so `update_blocks` can delete them
+/// when the block is orphaned by a reorg instead of leaving stale registry entries behind.
+#[entity]
+pub struct BlockMintedTokens {
+    #[fk(one2one)]
+    pub id: BlockHeight,
+    pub tokens: Vec<MintedTokenRef>,
+}
+
+#[entity]
+pub struct MintedTokenRef {
+    #[fk(one2many)]
+    pub id: MintedTokenPointer,
+    #[column]
+    pub asset_id: MintedAssetId,
 }
 
 #[entity]
@@ -98,6 +154,23 @@ pub struct Utxo {
     pub assets: Vec<Asset>,
 }
 
+/// Classifies an `Asset` line relative to the `TokenRegistry`: a genuine issuance event
+/// versus a later transfer of an already-registered token.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AssetType {
+    Mint,
+    Transfer,
+}
+
+impl From<AssetType> for u8 {
+    fn from(asset_type: AssetType) -> Self {
+        match asset_type {
+            AssetType::Mint => 0,
+            AssetType::Transfer => 1,
+        }
+    }
+}
+
 #[entity]
 pub struct Asset {
     #[fk(one2many, range)]
@@ -116,6 +189,72 @@ pub struct InputRef {
     pub id: InputPointer,
 }
 
+#[root_key]
+pub struct BalanceAddress(pub Vec<u8>);
+
+/// `u16`, not `u8`: unlike `AssetPointer` (bounded per-UTXO) or `TokenDeltaPointer` (bounded
+/// per-block), this accumulates across an address's entire history, and long-lived or
+/// NFT-heavy addresses routinely hold more than 255 distinct token types.
+#[pointer_key(u16)]
+pub struct TokenBalancePointer(BalanceAddress);
+
+#[pointer_key(u16)]
+pub struct AddressDeltaPointer(BlockHeight);
+
+#[pointer_key(u8)]
+pub struct TokenDeltaPointer(AddressDeltaPointer);
+
+/// Materialized, reorg-safe running balance for an address, kept up to date incrementally
+/// instead of being recomputed from the full UTXO set on every query.
+#[entity]
+pub struct AddressBalance {
+    #[pk]
+    pub id: BalanceAddress,
+    #[column]
+    pub nano_erg: u64,
+    pub tokens: Vec<TokenBalance>,
+}
+
+#[entity]
+pub struct TokenBalance {
+    #[fk(one2many)]
+    pub id: TokenBalancePointer,
+    #[column(index, dictionary)]
+    pub name: AssetName,
+    #[column]
+    pub amount: u64,
+}
+
+/// Net balance delta produced by a single block, kept so `update_blocks` can undo it on reorg
+/// before the replacement blocks are applied.
+#[entity]
+pub struct BlockBalanceDelta {
+    #[fk(one2one)]
+    pub id: BlockHeight,
+    pub address_deltas: Vec<AddressDelta>,
+}
+
+#[entity]
+pub struct AddressDelta {
+    #[fk(one2many)]
+    pub id: AddressDeltaPointer,
+    #[column(index)]
+    pub address: BalanceAddress,
+    #[column]
+    pub nano_erg_delta: i64,
+    pub token_deltas: Vec<TokenDelta>,
+}
+
+#[entity]
+pub struct TokenDelta {
+    #[fk(one2many)]
+    pub id: TokenDeltaPointer,
+    #[column]
+    pub name: AssetName,
+    #[column]
+    pub amount_delta: i64,
+}
+
 impl BlockHeaderLike for BlockHeader {
     fn height(&self) -> u32 {
         self.id.0